@@ -0,0 +1,381 @@
+//! A serde [`Deserializer`](serde::Deserializer) that decodes bencode
+//! directly into Rust types, without building an intermediate [`Value`]
+//! tree first.
+
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::Value;
+
+/// Errors produced while deserializing bencode into a Rust type.
+#[derive(Debug)]
+pub enum Error {
+    /// The input ended before a complete value could be read.
+    Eof,
+    /// A value parsed successfully but bytes remained afterwards.
+    TrailingBytes,
+    /// The input did not look like a valid bencode value at all.
+    Syntax,
+    /// A dictionary's keys were not in ascending raw-byte order, or repeated
+    /// a previous key, violating the same canonical-form rule enforced by
+    /// `Value::parse`.
+    UnsortedKeys,
+    /// A byte string was expected to be UTF-8 but was not.
+    InvalidUtf8,
+    /// Any other error, usually from a `Deserialize` impl.
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Eof => write!(f, "unexpected end of bencode input"),
+            Error::TrailingBytes => write!(f, "trailing bytes after the decoded value"),
+            Error::Syntax => write!(f, "invalid bencode syntax"),
+            Error::UnsortedKeys => write!(f, "dictionary keys are not sorted"),
+            Error::InvalidUtf8 => write!(f, "byte string is not valid utf-8"),
+            Error::Custom(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+impl<I> From<nom::Err<crate::BencodeError<I>>> for Error {
+    fn from(_: nom::Err<crate::BencodeError<I>>) -> Self {
+        Error::Syntax
+    }
+}
+
+/// Deserializes `T` from bencode bytes.
+///
+/// Byte strings are borrowed from `input` where the target type allows it,
+/// so this avoids allocating for the common case of decoding into a struct
+/// of `&[u8]`/`&str` fields.
+pub fn from_bytes<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
+    let mut deserializer = Deserializer::from_bytes(input);
+    let value = T::deserialize(&mut deserializer)?;
+    if deserializer.input.is_empty() {
+        Ok(value)
+    } else {
+        Err(Error::TrailingBytes)
+    }
+}
+
+/// A serde deserializer over a bencode byte buffer.
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        Deserializer { input }
+    }
+
+    fn peek(&self) -> Result<u8, Error> {
+        self.input.first().copied().ok_or(Error::Eof)
+    }
+
+    fn bump(&mut self) {
+        self.input = &self.input[1..];
+    }
+
+    fn parse_integer(&mut self) -> Result<i64, Error> {
+        let (next, value) = Value::parse_integer(self.input)?;
+        self.input = next;
+        match value {
+            Value::Integer(i) => Ok(i),
+            _ => unreachable!(),
+        }
+    }
+
+    fn parse_bytes(&mut self) -> Result<&'de [u8], Error> {
+        let (next, value) = Value::parse_bytes(self.input)?;
+        self.input = next;
+        match value {
+            Value::Bytes(b) => Ok(b),
+            _ => unreachable!(),
+        }
+    }
+
+    fn parse_str(&mut self) -> Result<&'de str, Error> {
+        std::str::from_utf8(self.parse_bytes()?).map_err(|_| Error::InvalidUtf8)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.peek()? {
+            b'i' => visitor.visit_i64(self.parse_integer()?),
+            b'0'..=b'9' => {
+                let bytes = self.parse_bytes()?;
+                match std::str::from_utf8(bytes) {
+                    Ok(s) => visitor.visit_borrowed_str(s),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
+            b'l' => self.deserialize_seq(visitor),
+            b'd' => self.deserialize_map(visitor),
+            _ => Err(Error::Syntax),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(self.parse_integer()? != 0)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.parse_str()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_bytes(self.parse_bytes()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Bencode has no null; every present field is `Some`.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.peek()? != b'l' {
+            return Err(Error::Syntax);
+        }
+        self.bump();
+        let value = visitor.visit_seq(BencodeSeq { de: self })?;
+        if self.peek()? != b'e' {
+            return Err(Error::Syntax);
+        }
+        self.bump();
+        Ok(value)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.peek()? != b'd' {
+            return Err(Error::Syntax);
+        }
+        self.bump();
+        let value = visitor.visit_map(BencodeMap {
+            de: self,
+            prev_key: None,
+        })?;
+        if self.peek()? != b'e' {
+            return Err(Error::Syntax);
+        }
+        self.bump();
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        // Unit variants are plain byte strings; any other variant is an
+        // externally-tagged single-entry dict: `d<variant><content>e`.
+        if self.peek()? == b'd' {
+            self.bump();
+            let value = visitor.visit_enum(BencodeEnum { de: self })?;
+            if self.peek()? != b'e' {
+                return Err(Error::Syntax);
+            }
+            self.bump();
+            Ok(value)
+        } else {
+            visitor.visit_enum(self.parse_str()?.into_deserializer())
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // No dedicated unit encoding; consume whatever value is present.
+        let (next, _) = Value::parse_integer(self.input)
+            .or_else(|_| Value::parse_bytes(self.input))
+            .or_else(|_| Value::parse_list(self.input))
+            .or_else(|_| Value::parse_dict(self.input))?;
+        self.input = next;
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char string
+        byte_buf unit_struct tuple tuple_struct struct identifier ignored_any
+    }
+}
+
+struct BencodeSeq<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> SeqAccess<'de> for BencodeSeq<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.de.peek()? == b'e' {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct BencodeMap<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    /// The previous entry's raw key bytes, so `next_key_seed` can enforce
+    /// the same ascending-order/no-duplicates rule as `Value::parse_dict`.
+    prev_key: Option<&'de [u8]>,
+}
+
+impl<'de, 'a> MapAccess<'de> for BencodeMap<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        if self.de.peek()? == b'e' {
+            return Ok(None);
+        }
+
+        // Peek the raw key bytes to check ordering before letting `seed`
+        // (which may want a `&str`, `String`, field identifier, etc.)
+        // consume them through the normal deserialization path.
+        let (_, key) = Value::parse_bytes(self.de.input)?;
+        let key = match key {
+            Value::Bytes(key) => key,
+            _ => unreachable!(),
+        };
+        if let Some(prev_key) = self.prev_key {
+            if key <= prev_key {
+                return Err(Error::UnsortedKeys);
+            }
+        }
+        self.prev_key = Some(key);
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct BencodeEnum<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for BencodeEnum<'a, 'de> {
+    type Error = Error;
+    type Variant = &'a mut Deserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self.de))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_bytes;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct File<'a> {
+        length: i64,
+        #[serde(borrow)]
+        path: Vec<&'a str>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Info<'a> {
+        name: &'a str,
+        #[serde(rename = "piece length")]
+        piece_length: i64,
+        #[serde(borrow)]
+        files: Vec<File<'a>>,
+    }
+
+    #[test]
+    fn deserialize_struct_borrows_byte_strings() {
+        // Keys sorted ascending by raw bytes ("files" < "name" < "piece
+        // length"), since `next_key_seed` now enforces the same order as
+        // `Value::parse_dict`.
+        let input =
+            b"d5:filesld6:lengthi10e4:pathl1:a1:beee4:name6:debian12:piece lengthi512ee";
+
+        let info: Info = from_bytes(input).unwrap();
+        assert_eq!(
+            info,
+            Info {
+                name: "debian",
+                piece_length: 512,
+                files: vec![File {
+                    length: 10,
+                    path: vec!["a", "b"],
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_rejects_trailing_bytes() {
+        let err = from_bytes::<i64>(b"i1eextra").unwrap_err();
+        assert!(matches!(err, super::Error::TrailingBytes));
+    }
+
+    #[test]
+    fn deserialize_rejects_unsorted_or_duplicate_keys() {
+        use std::collections::BTreeMap;
+
+        let unsorted = b"d3:foo3:bar3:bar3:baze";
+        let err = from_bytes::<BTreeMap<&str, &str>>(unsorted).unwrap_err();
+        assert!(matches!(err, super::Error::UnsortedKeys));
+
+        let duplicate = b"d3:bar3:bar3:bar3:baze";
+        let err = from_bytes::<BTreeMap<&str, &str>>(duplicate).unwrap_err();
+        assert!(matches!(err, super::Error::UnsortedKeys));
+    }
+}
@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io::{self, Write};
 use std::num::ParseIntError;
-use std::{collections::HashMap, fmt::Debug};
 
 use nom::combinator::eof;
 use nom::multi::many0;
@@ -14,12 +16,23 @@ use nom::{
     Err, IResult,
 };
 
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "serde")]
+mod ser;
+
+#[cfg(feature = "serde")]
+pub use de::{from_bytes, Deserializer};
+#[cfg(feature = "serde")]
+pub use ser::{to_bytes, Serializer};
+
 #[derive(Debug)]
 pub enum BencodeError<I> {
     Nom(I, ErrorKind),
     InvalidInteger(I),
     ParseIntError(I, ParseIntError),
     InvalidBytesLength(I),
+    UnsortedKeys(I),
 }
 
 impl<I> ParseError<I> for BencodeError<I> {
@@ -42,12 +55,16 @@ impl<I> From<BencodeError<I>> for nom::Err<BencodeError<I>> {
 
 type BencodeResult<'a> = IResult<&'a [u8], Value<'a>, BencodeError<&'a [u8]>>;
 
+/// Maps a dictionary path (the keys leading to a value, root-first) to the
+/// exact bytes of `input` it was decoded from. See [`Value::parse_spanned`].
+pub type SpanMap<'a> = BTreeMap<Vec<&'a [u8]>, &'a [u8]>;
+
 #[derive(Debug, Clone)]
 pub enum Value<'a> {
     Bytes(&'a [u8]),
     Integer(i64),
     List(Vec<Self>),
-    Dictionary(HashMap<&'a [u8], Self>),
+    Dictionary(BTreeMap<&'a [u8], Self>),
 }
 
 impl<'a> Value<'a> {
@@ -124,14 +141,25 @@ impl<'a> Value<'a> {
             ),
         )(input)?;
 
-        let data = value.0.into_iter().map(|x| {
-            if let Value::Bytes(key) = x.0 {
-                (key, x.1)
+        let mut dict = BTreeMap::new();
+        let mut prev_key: Option<&'a [u8]> = None;
+        for (key, item) in value.0 {
+            let key = if let Value::Bytes(key) = key {
+                key
             } else {
                 unreachable!()
+            };
+
+            if let Some(prev_key) = prev_key {
+                if key <= prev_key {
+                    Err(BencodeError::UnsortedKeys(input))?;
+                }
             }
-        });
-        Ok((next, Value::Dictionary(data.collect())))
+            prev_key = Some(key);
+
+            dict.insert(key, item);
+        }
+        Ok((next, Value::Dictionary(dict)))
     }
 
     pub fn parse(input: &[u8]) -> Result<Vec<Value>, Err<BencodeError<&[u8]>>> {
@@ -145,11 +173,270 @@ impl<'a> Value<'a> {
         let _ = eof(next)?;
         Ok(result)
     }
+
+    /// Parses a single top-level value, also returning the exact sub-slice of
+    /// `input` each dictionary entry was decoded from.
+    ///
+    /// This lets a caller locate, e.g., the raw encoding of the `info` entry
+    /// of a torrent and hash it directly, without re-encoding it (which would
+    /// only work if it happened to already be in canonical form).
+    ///
+    /// The map is keyed by the sequence of dictionary keys leading to a
+    /// value, e.g. `&[b"info"]` or `&[b"info", b"name"]`; the root value
+    /// itself is keyed by the empty path. Values nested under a list are not
+    /// individually tracked, since bencode dictionaries (not lists) are the
+    /// only place an info-hash-style lookup is needed in practice: a list is
+    /// parsed with the plain, non-spanned parsers (so a list of dicts, e.g.
+    /// `info.files`, can't have its entries' keys collide on one shared
+    /// path).
+    pub fn parse_spanned(input: &'a [u8]) -> Result<(Value<'a>, SpanMap<'a>), Err<BencodeError<&'a [u8]>>> {
+        let mut spans = BTreeMap::new();
+        let mut path = Vec::new();
+        let (next, value) = Self::parse_spanned_value(input, &mut path, &mut spans)?;
+        let _ = eof(next)?;
+        Ok((value, spans))
+    }
+
+    fn parse_spanned_value(
+        input: &'a [u8],
+        path: &mut Vec<&'a [u8]>,
+        spans: &mut SpanMap<'a>,
+    ) -> BencodeResult<'a> {
+        let (next, value) = alt((
+            Self::parse_bytes,
+            Self::parse_integer,
+            Self::parse_list,
+            |i| Self::parse_dict_spanned(i, path, spans),
+        ))(input)?;
+
+        let consumed = input.len() - next.len();
+        spans.insert(path.clone(), &input[..consumed]);
+        Ok((next, value))
+    }
+
+    /// Like `parse_dict`, but additionally threads `path`/`spans` through so
+    /// each entry's value records the raw bytes it was decoded from. This
+    /// can't simply delegate to `parse_dict`'s `many_till`-based combinator,
+    /// since it needs to push the just-parsed key onto `path` before
+    /// recursing into the entry's value; the sorted/duplicate key check is
+    /// therefore duplicated here and must be kept in sync with `parse_dict`.
+    fn parse_dict_spanned(
+        input: &'a [u8],
+        path: &mut Vec<&'a [u8]>,
+        spans: &mut SpanMap<'a>,
+    ) -> BencodeResult<'a> {
+        let (mut rest, _) = char('d')(input)?;
+        let mut dict = BTreeMap::new();
+        let mut prev_key: Option<&'a [u8]> = None;
+
+        loop {
+            if rest.first() == Some(&b'e') {
+                rest = &rest[1..];
+                break;
+            }
+
+            let (next, key) = Self::parse_bytes(rest)?;
+            let key = match key {
+                Value::Bytes(key) => key,
+                _ => unreachable!(),
+            };
+
+            if let Some(prev_key) = prev_key {
+                if key <= prev_key {
+                    Err(BencodeError::UnsortedKeys(input))?;
+                }
+            }
+            prev_key = Some(key);
+
+            path.push(key);
+            let (next, value) = Self::parse_spanned_value(next, path, spans)?;
+            path.pop();
+
+            dict.insert(key, value);
+            rest = next;
+        }
+        Ok((rest, Value::Dictionary(dict)))
+    }
+
+    /// Serializes this value to its canonical bencode representation.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf)
+            .expect("writing to a Vec<u8> cannot fail");
+        buf
+    }
+
+    /// Streams this value's canonical bencode representation into `writer`.
+    ///
+    /// `Dictionary` is backed by a `BTreeMap`, so keys are always iterated in
+    /// ascending raw-byte order, matching the order `parse_dict` requires on
+    /// the way in. Output round-trips byte-for-byte through [`Value::parse`].
+    pub fn encode_into<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Value::Bytes(bytes) => {
+                write!(writer, "{}:", bytes.len())?;
+                writer.write_all(bytes)?;
+            }
+            Value::Integer(i) => write!(writer, "i{i}e")?,
+            Value::List(items) => {
+                writer.write_all(b"l")?;
+                for item in items {
+                    item.encode_into(writer)?;
+                }
+                writer.write_all(b"e")?;
+            }
+            Value::Dictionary(dict) => {
+                writer.write_all(b"d")?;
+                for (key, value) in dict {
+                    write!(writer, "{}:", key.len())?;
+                    writer.write_all(key)?;
+                    value.encode_into(writer)?;
+                }
+                writer.write_all(b"e")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the inner byte string, if this is a [`Value::Bytes`].
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner byte string as a UTF-8 `str`, if this is a
+    /// [`Value::Bytes`] containing valid UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        std::str::from_utf8(self.as_bytes()?).ok()
+    }
+
+    /// Returns the inner integer, if this is a [`Value::Integer`].
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner list, if this is a [`Value::List`].
+    pub fn as_list(&self) -> Option<&[Self]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner dictionary, if this is a [`Value::Dictionary`].
+    pub fn as_dict(&self) -> Option<&BTreeMap<&'a [u8], Self>> {
+        match self {
+            Value::Dictionary(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` in this value's dictionary.
+    ///
+    /// Returns `None` if this value is not a [`Value::Dictionary`] or the key
+    /// is absent.
+    pub fn get(&self, key: &[u8]) -> Option<&Self> {
+        self.as_dict()?.get(key)
+    }
+
+    /// Walks a sequence of dictionary keys, returning the value found at the
+    /// end of the path, e.g. `root.path(&[b"info", b"name"])`.
+    ///
+    /// Returns `None` as soon as a key is missing or an intermediate value is
+    /// not a dictionary.
+    pub fn path(&self, keys: &[&[u8]]) -> Option<&Self> {
+        let mut current = self;
+        for key in keys {
+            current = current.get(key)?;
+        }
+        Some(current)
+    }
+
+    /// Detaches this value from the buffer it was parsed from, copying every
+    /// byte string into its own allocation.
+    ///
+    /// Useful for storing a parsed value past the lifetime of the input, or
+    /// moving it across threads; the borrowed `Value` remains the fast path
+    /// for transient parsing.
+    pub fn into_owned(self) -> OwnedValue {
+        match self {
+            Value::Bytes(bytes) => OwnedValue::Bytes(bytes.to_vec()),
+            Value::Integer(i) => OwnedValue::Integer(i),
+            Value::List(items) => {
+                OwnedValue::List(items.into_iter().map(Value::into_owned).collect())
+            }
+            Value::Dictionary(dict) => OwnedValue::Dictionary(
+                dict.into_iter()
+                    .map(|(key, value)| (key.to_vec(), value.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// An owned, `'static` counterpart to [`Value`].
+///
+/// Produced by [`Value::into_owned`] when a caller needs to keep parsed data
+/// alive past the lifetime of the source bytes, or wants to build a value by
+/// hand (rather than by parsing) before encoding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedValue {
+    Bytes(Vec<u8>),
+    Integer(i64),
+    List(Vec<Self>),
+    Dictionary(BTreeMap<Vec<u8>, Self>),
+}
+
+impl OwnedValue {
+    /// Serializes this value to its canonical bencode representation. See
+    /// [`Value::encode`].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf)
+            .expect("writing to a Vec<u8> cannot fail");
+        buf
+    }
+
+    /// Streams this value's canonical bencode representation into `writer`.
+    /// See [`Value::encode_into`].
+    pub fn encode_into<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            OwnedValue::Bytes(bytes) => {
+                write!(writer, "{}:", bytes.len())?;
+                writer.write_all(bytes)?;
+            }
+            OwnedValue::Integer(i) => write!(writer, "i{i}e")?,
+            OwnedValue::List(items) => {
+                writer.write_all(b"l")?;
+                for item in items {
+                    item.encode_into(writer)?;
+                }
+                writer.write_all(b"e")?;
+            }
+            OwnedValue::Dictionary(dict) => {
+                writer.write_all(b"d")?;
+                for (key, value) in dict {
+                    write!(writer, "{}:", key.len())?;
+                    writer.write_all(key)?;
+                    value.encode_into(writer)?;
+                }
+                writer.write_all(b"e")?;
+            }
+        }
+        Ok(())
+    }
 }
+
 #[cfg(test)]
 mod tests {
-    use crate::{BencodeError, Value};
+    use crate::{BencodeError, OwnedValue, Value};
     use assert_matches::assert_matches;
+    use std::collections::BTreeMap;
 
     #[test]
     fn parse_integer() {
@@ -259,6 +546,12 @@ mod tests {
 
         let v = Value::parse_dict(b"d:bar4:spam3:fooi42e").unwrap_err();
         assert_matches!(v, nom::Err::Error(BencodeError::Nom(..)));
+
+        let v = Value::parse_dict(b"d3:foo3:bar3:bar3:baze").unwrap_err();
+        assert_matches!(v, nom::Err::Failure(BencodeError::UnsortedKeys(_)));
+
+        let v = Value::parse_dict(b"d3:bar3:bar3:bar3:baze").unwrap_err();
+        assert_matches!(v, nom::Err::Failure(BencodeError::UnsortedKeys(_)));
     }
 
     #[test]
@@ -346,6 +639,127 @@ mod tests {
         assert_matches!(v, nom::Err::Error(BencodeError::Nom(..)));
     }
 
+    #[test]
+    fn encode_roundtrips_primitives() {
+        assert_eq!(Value::Integer(1337).encode(), b"i1337e");
+        assert_eq!(Value::Integer(-9).encode(), b"i-9e");
+        assert_eq!(Value::Bytes(b"spam").encode(), b"4:spam");
+        assert_eq!(Value::List(vec![]).encode(), b"le");
+        assert_eq!(
+            Value::List(vec![Value::Integer(1), Value::Bytes(b"a")]).encode(),
+            b"li1e1:ae"
+        );
+    }
+
+    #[test]
+    fn encode_sorts_dictionary_keys_by_raw_bytes() {
+        let mut dict = BTreeMap::new();
+        dict.insert(b"zebra".as_slice(), Value::Integer(1));
+        dict.insert(b"apple".as_slice(), Value::Integer(2));
+        dict.insert(b"Apple".as_slice(), Value::Integer(3));
+
+        let encoded = Value::Dictionary(dict).encode();
+        assert_eq!(encoded, b"d5:Applei3e5:applei2e5:zebrai1ee");
+    }
+
+    #[test]
+    fn encode_parse_torrent_roundtrip() {
+        let bytes = include_bytes!("../test-assets/test.torrent");
+        let data = Value::parse(bytes).unwrap();
+        let v = data.first().unwrap();
+        assert_eq!(v.encode(), bytes.as_slice());
+    }
+
+    #[test]
+    fn accessors() {
+        let (_, v) = Value::parse_dict(b"d3:bar4:spam3:fooli42eee").unwrap();
+
+        assert_eq!(v.as_bytes(), None);
+        assert_eq!(v.as_dict().unwrap().len(), 2);
+
+        let bar = v.get(b"bar").unwrap();
+        assert_eq!(bar.as_bytes(), Some(b"spam".as_slice()));
+        assert_eq!(bar.as_str(), Some("spam"));
+        assert_eq!(bar.as_i64(), None);
+
+        let foo = v.get(b"foo").unwrap();
+        assert_eq!(foo.as_list().unwrap().first().unwrap().as_i64(), Some(42));
+
+        assert!(v.get(b"missing").is_none());
+    }
+
+    #[test]
+    fn path_walks_nested_dictionaries() {
+        let bytes = include_bytes!("../test-assets/test.torrent");
+        let data = Value::parse(bytes).unwrap();
+        let root = data.first().unwrap();
+
+        let name = root.path(&[b"info", b"name"]).unwrap();
+        assert_eq!(name.as_str(), Some("debian-mac-12.1.0-amd64-netinst.iso"));
+
+        assert!(root.path(&[b"info", b"missing"]).is_none());
+        assert!(root.path(&[b"missing", b"name"]).is_none());
+    }
+
+    #[test]
+    fn parse_spanned_tracks_dictionary_entry_spans() {
+        let input = b"d3:bar4:spam3:fooli42eee";
+        let (value, spans) = Value::parse_spanned(input).unwrap();
+        assert_matches!(value, Value::Dictionary(_));
+
+        assert_eq!(spans[&vec![]], input.as_slice());
+        assert_eq!(spans[&vec![b"bar".as_slice()]], b"4:spam".as_slice());
+        assert_eq!(spans[&vec![b"foo".as_slice()]], b"li42ee".as_slice());
+    }
+
+    #[test]
+    fn parse_spanned_does_not_collide_across_list_entries() {
+        // Shaped like a multi-file torrent's `info.files`: a list of dicts
+        // that each reuse the same key names.
+        let input = b"d5:filesld6:lengthi1e4:path1:aed6:lengthi2e4:path1:beee";
+        let (_, spans) = Value::parse_spanned(input).unwrap();
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[&vec![]], input.as_slice());
+        assert_eq!(
+            spans[&vec![b"files".as_slice()]],
+            b"ld6:lengthi1e4:path1:aed6:lengthi2e4:path1:bee".as_slice()
+        );
+    }
+
+    #[test]
+    fn parse_spanned_locates_raw_info_dict_for_hashing() {
+        let bytes = include_bytes!("../test-assets/test.torrent");
+        let (_, spans) = Value::parse_spanned(bytes.as_slice()).unwrap();
+
+        let info_span = spans[&vec![b"info".as_slice()]];
+        assert!(info_span.starts_with(b"d"));
+        assert!(info_span.ends_with(b"e"));
+
+        let (_, reparsed) = Value::parse_dict(info_span).unwrap();
+        assert_matches!(reparsed, Value::Dictionary(_));
+    }
+
+    #[test]
+    fn into_owned_detaches_from_the_input_buffer() {
+        let owned = {
+            let bytes = b"d3:bar4:spam3:fooli42eee".to_vec();
+            let (_, value) = Value::parse_dict(&bytes).unwrap();
+            value.into_owned()
+        };
+
+        assert_eq!(owned.encode(), b"d3:bar4:spam3:fooli42eee");
+
+        if let OwnedValue::Dictionary(dict) = &owned {
+            assert_eq!(
+                dict.get(b"bar".as_slice()),
+                Some(&OwnedValue::Bytes(b"spam".to_vec()))
+            );
+        } else {
+            panic!("expected a dictionary");
+        }
+    }
+
     #[test]
     fn test_parse_torrent() {
         let data = Value::parse(include_bytes!("../test-assets/test.torrent")).unwrap();
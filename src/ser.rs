@@ -0,0 +1,426 @@
+//! A serde [`Serializer`](serde::Serializer) that writes the canonical
+//! bencode form directly, reusing the same sorted-key rule as
+//! [`Value::encode`](crate::Value::encode).
+
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+/// Errors produced while serializing a Rust type to bencode.
+#[derive(Debug)]
+pub enum Error {
+    /// Bencode has no representation for floats, `None`/unit, or maps with
+    /// non-string keys.
+    Unrepresentable(&'static str),
+    /// Any other error, usually from a `Serialize` impl.
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unrepresentable(what) => write!(f, "bencode cannot represent {what}"),
+            Error::Custom(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Serializes `value` to its canonical bencode representation.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    value.serialize(&mut Serializer {
+        output: &mut output,
+    })?;
+    Ok(output)
+}
+
+fn write_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.extend_from_slice(bytes.len().to_string().as_bytes());
+    output.push(b':');
+    output.extend_from_slice(bytes);
+}
+
+/// Writes a `d...e` dict with `entries` sorted by raw key bytes.
+fn write_dict(output: &mut Vec<u8>, mut entries: Vec<(Vec<u8>, Vec<u8>)>) {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    output.push(b'd');
+    for (key, value) in &entries {
+        write_bytes(output, key);
+        output.extend_from_slice(value);
+    }
+    output.push(b'e');
+}
+
+/// A serde serializer that writes canonical bencode into a `Vec<u8>`.
+pub struct Serializer<'a> {
+    output: &'a mut Vec<u8>,
+}
+
+impl<'a, 'b> ser::Serializer for &'a mut Serializer<'b> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = BencodeSeq<'a, 'b>;
+    type SerializeTuple = BencodeSeq<'a, 'b>;
+    type SerializeTupleStruct = BencodeSeq<'a, 'b>;
+    type SerializeTupleVariant = BencodeSeq<'a, 'b>;
+    type SerializeMap = BencodeMap<'a, 'b>;
+    type SerializeStruct = BencodeMap<'a, 'b>;
+    type SerializeStructVariant = BencodeMap<'a, 'b>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.output.extend_from_slice(format!("i{v}e").as_bytes());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.serialize_i64(
+            i64::try_from(v).map_err(|_| Error::Custom("integer too large for i64".into()))?,
+        )
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), Error> {
+        Err(Error::Unrepresentable("floating point numbers"))
+    }
+    fn serialize_f64(self, _v: f64) -> Result<(), Error> {
+        Err(Error::Unrepresentable("floating point numbers"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        write_bytes(self.output, v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(Error::Unrepresentable("null (use skip_serializing_if)"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(Error::Unrepresentable("unit values"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        write_dict(
+            self.output,
+            vec![(variant.as_bytes().to_vec(), to_bytes(value)?)],
+        );
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        self.output.push(b'l');
+        Ok(BencodeSeq { ser: self })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        self.output.push(b'd');
+        write_bytes(self.output, variant.as_bytes());
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(BencodeMap {
+            ser: self,
+            entries: Vec::new(),
+            pending_key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        let mut map = self.serialize_map(Some(len))?;
+        map.variant = Some(variant);
+        Ok(map)
+    }
+}
+
+/// Writes a bencode list (`l...e`) element by element.
+pub struct BencodeSeq<'a, 'b> {
+    ser: &'a mut Serializer<'b>,
+}
+
+impl<'a, 'b> ser::SerializeSeq for BencodeSeq<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.ser.output.push(b'e');
+        Ok(())
+    }
+}
+
+impl<'a, 'b> ser::SerializeTuple for BencodeSeq<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, 'b> ser::SerializeTupleStruct for BencodeSeq<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, 'b> ser::SerializeTupleVariant for BencodeSeq<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        // `SerializeSeq::end` would consume `self` to close the list, but we
+        // still need `self.ser` afterwards to close the enclosing dict, so
+        // write both closing `e`s directly instead of delegating.
+        self.ser.output.push(b'e');
+        self.ser.output.push(b'e');
+        Ok(())
+    }
+}
+
+/// Writes a bencode dict (`d...e`), buffering entries so they can be
+/// emitted sorted by raw key bytes, per the canonical form.
+pub struct BencodeMap<'a, 'b> {
+    ser: &'a mut Serializer<'b>,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    pending_key: Option<Vec<u8>>,
+    variant: Option<&'static str>,
+}
+
+impl<'a, 'b> BencodeMap<'a, 'b> {
+    fn finish(self) -> Result<(), Error> {
+        write_dict(self.ser.output, self.entries);
+        Ok(())
+    }
+}
+
+impl<'a, 'b> ser::SerializeMap for BencodeMap<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        // Keys are stored as raw bytes (`write_dict` adds the length prefix),
+        // so strip the `<len>:` prefix and reject anything not a byte string.
+        let encoded = to_bytes(key)?;
+        match crate::Value::parse_bytes(&encoded) {
+            Ok((rest, crate::Value::Bytes(raw))) if rest.is_empty() => {
+                self.pending_key = Some(raw.to_vec());
+                Ok(())
+            }
+            _ => Err(Error::Unrepresentable("maps with non-string keys")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .pending_key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.entries.push((key, to_bytes(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        match self.variant {
+            Some(variant) => {
+                let mut inner = Vec::new();
+                write_dict(&mut inner, self.entries);
+                write_dict(self.ser.output, vec![(variant.as_bytes().to_vec(), inner)]);
+                Ok(())
+            }
+            None => self.finish(),
+        }
+    }
+}
+
+impl<'a, 'b> ser::SerializeStruct for BencodeMap<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.entries.push((key.as_bytes().to_vec(), to_bytes(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl<'a, 'b> ser::SerializeStructVariant for BencodeMap<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_bytes;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Torrent<'a> {
+        #[serde(rename = "created by")]
+        created_by: &'a str,
+        announce: &'a str,
+    }
+
+    #[test]
+    fn serialize_struct_sorts_keys() {
+        let torrent = Torrent {
+            created_by: "mktorrent",
+            announce: "http://tracker",
+        };
+
+        // "announce" < "created by" in raw byte order, so it must come first
+        // even though the struct field is declared second.
+        assert_eq!(
+            to_bytes(&torrent).unwrap(),
+            b"d8:announce14:http://tracker10:created by9:mktorrente".as_slice()
+        );
+    }
+
+    #[test]
+    fn serialize_primitives() {
+        assert_eq!(to_bytes(&1337i64).unwrap(), b"i1337e");
+        assert_eq!(to_bytes(&"spam").unwrap(), b"4:spam");
+        assert_eq!(to_bytes(&vec![1, 2, 3]).unwrap(), b"li1ei2ei3ee");
+    }
+}
+